@@ -1,25 +1,428 @@
-use tauri::{image::Image, Manager};
-use tauri_plugin_shell::{process::CommandChild, ShellExt};
-use std::sync::Mutex;
+use tauri::{image::Image, AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::{process::CommandChild, process::CommandEvent, ShellExt};
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+// Requires `tokio` (features "time", "sync", "macros") and, on Unix, `nix` (feature "signal")
+// as direct dependencies in Cargo.toml — this crate has no tracked manifest
+// yet, so they can't be added/verified from here.
+use tokio::sync::Notify;
 
-struct BackendProcess(Mutex<Option<CommandChild>>);
+/// Logical name of the main sidecar, registered at startup. Other sidecars
+/// (a vector DB, a worker process, ...) register under their own name.
+const BACKEND_NAME: &str = "crowforge-backend";
+
+/// Base delay before the first restart attempt; doubles on each
+/// consecutive failure up to `RESTART_BACKOFF_CAP`.
+const RESTART_BACKOFF_BASE: Duration = Duration::from_millis(500);
+const RESTART_BACKOFF_CAP: Duration = Duration::from_secs(30);
+/// Once a process has stayed up this long, its backoff resets to base.
+const RESTART_HEALTHY_THRESHOLD: Duration = Duration::from_secs(10);
+/// At most this many restarts within `RESTART_WINDOW`; the next crash
+/// after that gives up.
+const RESTART_MAX_ATTEMPTS: usize = 5;
+const RESTART_WINDOW: Duration = Duration::from_secs(60);
+
+/// How long to wait for a process to exit on its own after SIGTERM before
+/// escalating to a hard kill.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(3);
+const SHUTDOWN_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+#[cfg(unix)]
+fn send_sigterm(pid: u32) {
+    use nix::sys::signal::{kill, Signal};
+    use nix::unistd::Pid;
+    let _ = kill(Pid::from_raw(pid as i32), Signal::SIGTERM);
+}
+
+/// Unix has no portable "is this pid still running" query short of
+/// waitpid, which we don't own here since `CommandChild` reaps it
+/// internally — sending signal 0 is the standard liveness probe instead.
+#[cfg(unix)]
+fn is_process_alive(pid: u32) -> bool {
+    use nix::sys::signal::kill;
+    use nix::unistd::Pid;
+    kill(Pid::from_raw(pid as i32), None).is_ok()
+}
+
+/// Polls `child` until it exits or `deadline` passes, then escalates to a
+/// hard kill. Shared by single-process and bulk graceful shutdown so the
+/// latter can poll several children against one common deadline instead of
+/// serially waiting a full grace period per child.
+fn finish_graceful_kill(child: CommandChild, deadline: Instant) {
+    #[cfg(unix)]
+    {
+        let pid = child.pid();
+        while Instant::now() < deadline {
+            if !is_process_alive(pid) {
+                return;
+            }
+            std::thread::sleep(SHUTDOWN_POLL_INTERVAL);
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = deadline;
+    }
+
+    let _ = child.kill();
+}
+
+/// Reported to the frontend by `backend_status` so a status indicator can
+/// show whether a sidecar is currently up.
+#[derive(Clone, serde::Serialize)]
+struct BackendStatus {
+    running: bool,
+    pid: Option<u32>,
+}
+
+/// One supervised sidecar: holds its current child so it can be killed on
+/// shutdown, and tracks whether its supervisor loop is still allowed to
+/// respawn it after an unexpected exit.
+struct ManagedProcess {
+    child: Mutex<Option<CommandChild>>,
+    /// Cleared by `kill_inner`/`kill_graceful` so a deliberate stop doesn't
+    /// race with the supervisor loop respawning the process out from under
+    /// it. Set back to `true` to (re)enable supervision.
+    restarting: AtomicBool,
+    /// Bumped on every deliberate kill so the supervisor loop can tell an
+    /// explicit restart apart from an unexpected crash.
+    generation: AtomicU64,
+    /// True for as long as a `supervise()` task is alive for this process.
+    /// Gates `ProcessRegistry::spawn`/`restart` so at most one supervisor
+    /// loop ever runs per name.
+    supervisor_running: AtomicBool,
+    /// Wakes a supervisor that's sleeping out a backoff so a forced
+    /// restart takes effect immediately instead of waiting out the delay.
+    restart_notify: Notify,
+}
+
+impl ManagedProcess {
+    fn new() -> Self {
+        Self {
+            child: Mutex::new(None),
+            restarting: AtomicBool::new(true),
+            generation: AtomicU64::new(0),
+            supervisor_running: AtomicBool::new(false),
+            restart_notify: Notify::new(),
+        }
+    }
+
+    fn store_child(&self, child: CommandChild) {
+        if let Ok(mut guard) = self.child.lock() {
+            *guard = Some(child);
+        }
+    }
+
+    /// Drops the child handle once we know the process has exited, so a
+    /// stale `CommandChild` (and its possibly-recycled PID) never lingers
+    /// in the guard.
+    fn clear_child(&self) {
+        if let Ok(mut guard) = self.child.lock() {
+            *guard = None;
+        }
+    }
+
+    fn status(&self) -> BackendStatus {
+        let guard = match self.child.lock() {
+            Ok(guard) => guard,
+            Err(_) => return BackendStatus { running: false, pid: None },
+        };
+        match guard.as_ref() {
+            Some(child) => {
+                let pid = child.pid();
+                #[cfg(unix)]
+                let running = is_process_alive(pid);
+                #[cfg(not(unix))]
+                let running = true;
+                BackendStatus { running, pid: Some(pid) }
+            }
+            None => BackendStatus { running: false, pid: None },
+        }
+    }
 
-impl BackendProcess {
     fn kill_inner(&self) {
-        if let Ok(mut guard) = self.0.lock() {
+        self.restarting.store(false, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        if let Ok(mut guard) = self.child.lock() {
             if let Some(child) = guard.take() {
                 let _ = child.kill();
             }
         }
     }
+
+    /// Takes the child (if any) and sends it SIGTERM on Unix, without
+    /// waiting for it to exit. Paired with `finish_graceful_kill` so
+    /// callers can SIGTERM several processes up front and then poll them
+    /// all against one shared deadline.
+    fn begin_graceful_kill(&self) -> Option<CommandChild> {
+        self.restarting.store(false, Ordering::SeqCst);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        let child = match self.child.lock() {
+            Ok(mut guard) => guard.take(),
+            Err(_) => None,
+        };
+        #[cfg(unix)]
+        if let Some(child) = &child {
+            send_sigterm(child.pid());
+        }
+        child
+    }
+
+    /// Like `kill_inner`, but gives the process a chance to shut down
+    /// cleanly: on Unix, send SIGTERM and poll for exit up to
+    /// `SHUTDOWN_GRACE_PERIOD` before escalating to `child.kill()`
+    /// (SIGKILL). Windows has no graceful-stop signal for sidecars, so it
+    /// falls straight back to the hard kill.
+    fn kill_graceful(&self) {
+        if let Some(child) = self.begin_graceful_kill() {
+            finish_graceful_kill(child, Instant::now() + SHUTDOWN_GRACE_PERIOD);
+        }
+    }
 }
 
-impl Drop for BackendProcess {
+/// Registry of every sidecar the app has spawned, keyed by a logical name
+/// (`crowforge-backend`, plus whatever auxiliary services get registered
+/// alongside it). Guarantees every entry is reaped when the app exits for
+/// any reason, independent of the others.
+struct ProcessRegistry(Mutex<HashMap<String, Arc<ManagedProcess>>>);
+
+impl ProcessRegistry {
+    fn new() -> Self {
+        Self(Mutex::new(HashMap::new()))
+    }
+
+    fn entry(&self, name: &str) -> Arc<ManagedProcess> {
+        let mut map = self.0.lock().unwrap();
+        map.entry(name.to_string())
+            .or_insert_with(|| Arc::new(ManagedProcess::new()))
+            .clone()
+    }
+
+    fn get(&self, name: &str) -> Option<Arc<ManagedProcess>> {
+        self.0.lock().unwrap().get(name).cloned()
+    }
+
+    /// Registers `name` (if not already registered) and starts a
+    /// supervisor loop for it, unless one is already running — `spawn` is
+    /// idempotent, so it's safe to call from both app startup and the
+    /// `start_backend` command.
+    fn spawn(&self, app: &AppHandle, name: &str) {
+        let process = self.entry(name);
+        if process.supervisor_running.swap(true, Ordering::SeqCst) {
+            return;
+        }
+        process.restarting.store(true, Ordering::SeqCst);
+        tauri::async_runtime::spawn(supervise(app.clone(), name.to_string(), process));
+    }
+
+    /// Forces an immediate restart of `name`: kills the current child (if
+    /// any) and either wakes the existing supervisor loop to respawn right
+    /// away, or — if none is running — starts one. Never starts a second
+    /// supervisor loop alongside an existing one.
+    fn restart(&self, app: &AppHandle, name: &str) {
+        let process = self.entry(name);
+        process.kill_inner();
+        process.restarting.store(true, Ordering::SeqCst);
+        if process.supervisor_running.swap(true, Ordering::SeqCst) {
+            process.restart_notify.notify_waiters();
+        } else {
+            tauri::async_runtime::spawn(supervise(app.clone(), name.to_string(), process));
+        }
+    }
+
+    /// Kills `name` gracefully and disables its auto-restart.
+    fn kill(&self, name: &str) {
+        if let Some(process) = self.get(name) {
+            process.kill_graceful();
+        }
+    }
+
+    /// Kills every registered process gracefully. Sends SIGTERM to all of
+    /// them up front, then polls them against one shared deadline, so
+    /// total shutdown time is bounded by `SHUTDOWN_GRACE_PERIOD` rather
+    /// than that period multiplied by the number of processes. Called on
+    /// app exit so no sidecar outlives the app.
+    fn kill_all(&self) {
+        let processes: Vec<_> = self.0.lock().unwrap().values().cloned().collect();
+        let deadline = Instant::now() + SHUTDOWN_GRACE_PERIOD;
+        let pending: Vec<CommandChild> = processes
+            .iter()
+            .filter_map(|process| process.begin_graceful_kill())
+            .collect();
+        for child in pending {
+            finish_graceful_kill(child, deadline);
+        }
+    }
+}
+
+impl Drop for ProcessRegistry {
     fn drop(&mut self) {
-        self.kill_inner();
+        self.kill_all();
+    }
+}
+
+/// A single line of sidecar output, forwarded to the frontend so a log
+/// panel can render it without attaching an external terminal.
+#[derive(Clone, serde::Serialize)]
+struct BackendLogPayload {
+    process: String,
+    stream: &'static str,
+    level: &'static str,
+    line: String,
+    timestamp_ms: u128,
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0)
+}
+
+fn emit_backend_log(app: &AppHandle, process: &str, stream: &'static str, level: &'static str, line: String) {
+    let _ = app.emit(
+        "backend-log",
+        BackendLogPayload {
+            process: process.to_string(),
+            stream,
+            level,
+            line,
+            timestamp_ms: now_ms(),
+        },
+    );
+}
+
+/// Spawns the sidecar registered under `name` and drives it for as long as
+/// it keeps being supervised: forwards its stdout/stderr to the webview as
+/// `backend-log` events, and respawns it when it exits unexpectedly, with
+/// exponential backoff. An explicit restart (detected via the generation
+/// counter having changed while `restarting` is `true` again) skips the backoff and
+/// respawns right away instead of being counted as a crash. Gives up and
+/// emits `backend-crashed` after too many failures in a short window.
+async fn supervise(app: AppHandle, name: String, process: Arc<ManagedProcess>) {
+    let mut backoff = RESTART_BACKOFF_BASE;
+    let mut recent_restarts: VecDeque<Instant> = VecDeque::new();
+
+    loop {
+        // Re-checked on every iteration — including after waking from the
+        // backoff sleep below — so a stop that lands while we're asleep
+        // between children can't be raced by us spawning another one.
+        if !process.restarting.load(Ordering::SeqCst) {
+            break;
+        }
+        let generation = process.generation.load(Ordering::SeqCst);
+
+        let shell = app.shell();
+        let sidecar_command = match shell.sidecar(&name) {
+            Ok(cmd) => cmd,
+            Err(err) => {
+                emit_backend_log(&app, &name, "system", "error", format!("sidecar lookup failed: {err}"));
+                break;
+            }
+        };
+        let (mut rx, child) = match sidecar_command.spawn() {
+            Ok(pair) => pair,
+            Err(err) => {
+                emit_backend_log(&app, &name, "system", "error", format!("failed to spawn sidecar: {err}"));
+                break;
+            }
+        };
+        process.store_child(child);
+
+        let started_at = Instant::now();
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(bytes) => {
+                    emit_backend_log(&app, &name, "stdout", "info", String::from_utf8_lossy(&bytes).into_owned());
+                }
+                CommandEvent::Stderr(bytes) => {
+                    emit_backend_log(&app, &name, "stderr", "error", String::from_utf8_lossy(&bytes).into_owned());
+                }
+                CommandEvent::Terminated(payload) => {
+                    emit_backend_log(
+                        &app,
+                        &name,
+                        "system",
+                        "warn",
+                        format!("{name} exited (code={:?}, signal={:?})", payload.code, payload.signal),
+                    );
+                    break;
+                }
+                CommandEvent::Error(err) => {
+                    emit_backend_log(&app, &name, "system", "error", err);
+                }
+                _ => {}
+            }
+        }
+        process.clear_child();
+
+        if !process.restarting.load(Ordering::SeqCst) {
+            break;
+        }
+
+        if process.generation.load(Ordering::SeqCst) != generation {
+            // A deliberate kill (e.g. `restart_backend`) fired while this
+            // child was running — respawn immediately, not as a crash.
+            backoff = RESTART_BACKOFF_BASE;
+            continue;
+        }
+
+        if started_at.elapsed() >= RESTART_HEALTHY_THRESHOLD {
+            backoff = RESTART_BACKOFF_BASE;
+        }
+
+        let now = Instant::now();
+        while matches!(recent_restarts.front(), Some(t) if now.duration_since(*t) > RESTART_WINDOW) {
+            recent_restarts.pop_front();
+        }
+        if recent_restarts.len() >= RESTART_MAX_ATTEMPTS {
+            emit_backend_log(&app, &name, "system", "error", format!("{name} crashed too many times; giving up"));
+            let _ = app.emit("backend-crashed", name.clone());
+            process.restarting.store(false, Ordering::SeqCst);
+            break;
+        }
+        recent_restarts.push_back(now);
+
+        tokio::select! {
+            _ = tokio::time::sleep(backoff) => {}
+            _ = process.restart_notify.notified() => {}
+        }
+        backoff = (backoff * 2).min(RESTART_BACKOFF_CAP);
+    }
+
+    process.supervisor_running.store(false, Ordering::SeqCst);
+}
+
+#[tauri::command]
+fn backend_status(registry: State<ProcessRegistry>) -> BackendStatus {
+    match registry.get(BACKEND_NAME) {
+        Some(process) => process.status(),
+        None => BackendStatus { running: false, pid: None },
     }
 }
 
+/// Kills the current sidecar (if any) and respawns it right away.
+#[tauri::command]
+fn restart_backend(app: AppHandle, registry: State<ProcessRegistry>) {
+    registry.restart(&app, BACKEND_NAME);
+}
+
+/// Stops the sidecar and disables auto-restart until `start_backend` is
+/// called again.
+#[tauri::command]
+fn stop_backend(registry: State<ProcessRegistry>) {
+    registry.kill(BACKEND_NAME);
+}
+
+/// Starts the sidecar (and its supervisor loop) if it isn't already
+/// running — a no-op if it's currently up.
+#[tauri::command]
+fn start_backend(app: AppHandle, registry: State<ProcessRegistry>) {
+    registry.spawn(&app, BACKEND_NAME);
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
@@ -35,28 +438,31 @@ pub fn run() {
                 let _ = window.set_icon(icon);
             }
 
-            let shell = app.shell();
-            let sidecar_command = shell.sidecar("crowforge-backend")
-                .expect("crowforge-backend sidecar not found in bundle");
-            let (_rx, child) = sidecar_command.spawn()
-                .expect("failed to spawn crowforge-backend sidecar");
-
-            // Store child — Drop impl will kill it when Tauri exits for any reason
-            app.manage(BackendProcess(Mutex::new(Some(child))));
+            // Drop impl will kill every registered sidecar when Tauri exits
+            // for any reason; each has its own supervisor task respawning
+            // it on unexpected exits.
+            let registry = ProcessRegistry::new();
+            registry.spawn(app.handle(), BACKEND_NAME);
+            app.manage(registry);
             Ok(())
         })
         .on_window_event(|window, event| {
             match event {
                 // CloseRequested fires before the window closes — most reliable on Windows
                 tauri::WindowEvent::CloseRequested { .. } | tauri::WindowEvent::Destroyed => {
-                    if let Some(state) = window.try_state::<BackendProcess>() {
-                        state.kill_inner();
+                    if let Some(registry) = window.try_state::<ProcessRegistry>() {
+                        registry.kill_all();
                     }
                 }
                 _ => {}
             }
         })
-        .invoke_handler(tauri::generate_handler![])
+        .invoke_handler(tauri::generate_handler![
+            backend_status,
+            restart_backend,
+            stop_backend,
+            start_backend
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }